@@ -0,0 +1,215 @@
+use memmap::MmapMut;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use super::log::Error;
+
+/// Size in bytes of one index entry: a little-endian `u32` relative offset
+/// followed by a little-endian `u32` physical position.
+const ENTRY_SIZE: usize = 8;
+
+/// Size in bytes of the header stored at the start of the index file: a
+/// little-endian `u64` count of entries written so far. An entry's bytes
+/// alone can't distinguish "never written" from "legitimately zero" (the
+/// very first entry of a fresh index is `(0, 0)`), so the count is tracked
+/// explicitly instead of sniffed from zeroed slots.
+const HEADER_SIZE: usize = 8;
+
+/// A sparse offset index: a `{base_offset:020}.index` mmap file mapping
+/// logical message offsets to physical byte positions within the sibling
+/// `Log` segment. Entries are appended only every `index_interval` bytes
+/// written, not per record.
+#[derive(Debug)]
+pub struct Index {
+    file: File,
+    max_size: usize,
+    count: usize,
+    mmap: MmapMut,
+}
+
+impl Index {
+    pub fn new(
+        path: PathBuf,
+        base_offset: usize,
+        max_size: usize,
+        suffix: &str,
+    ) -> Result<Index, io::Error> {
+        fs::create_dir_all(&path).unwrap();
+        let index_path = path.join(format!("{:020}.index{}", base_offset, suffix));
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&index_path)
+            .unwrap();
+
+        file.set_len(max_size as u64)?;
+
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        let mut index = Index {
+            file,
+            max_size,
+            count: 0,
+            mmap,
+        };
+        index.recover();
+
+        Ok(index)
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.mmap.flush_async()?;
+        Ok(())
+    }
+
+    /// Grows the index file, if needed, to at least `max_size` bytes.
+    /// Mirrors `Occupancy::ensure_capacity` so the index keeps pace when a
+    /// growable segment outgrows the file size it was opened with.
+    pub fn ensure_capacity(&mut self, max_size: usize) -> Result<(), Error> {
+        if max_size <= self.max_size {
+            return Ok(());
+        }
+
+        self.mmap.flush_async()?;
+        self.file.set_len(max_size as u64)?;
+        self.mmap = unsafe { MmapMut::map_mut(&self.file)? };
+        self.max_size = max_size;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends an entry mapping `relative_offset` to `physical_position`.
+    pub fn append(&mut self, relative_offset: u32, physical_position: u32) -> Result<(), Error> {
+        let start = HEADER_SIZE + self.count * ENTRY_SIZE;
+        if start + ENTRY_SIZE > self.max_size {
+            return Err(Error::NoSpaceLeft);
+        }
+
+        (&mut self.mmap[start..start + 4]).write_all(&relative_offset.to_le_bytes())?;
+        (&mut self.mmap[start + 4..start + ENTRY_SIZE])
+            .write_all(&physical_position.to_le_bytes())?;
+        self.count += 1;
+        self.mmap[0..HEADER_SIZE].copy_from_slice(&(self.count as u64).to_le_bytes());
+
+        Ok(())
+    }
+
+    /// Restores `count` from the header written by prior `append` calls, so
+    /// a reopened index resumes appending (and answers `floor` queries)
+    /// exactly where it left off.
+    fn recover(&mut self) {
+        let count = u64::from_le_bytes(self.mmap[0..HEADER_SIZE].try_into().unwrap());
+        self.count = count as usize;
+    }
+
+    fn entry(&self, i: usize) -> (u32, u32) {
+        let start = HEADER_SIZE + i * ENTRY_SIZE;
+        let relative_offset = u32::from_le_bytes(self.mmap[start..start + 4].try_into().unwrap());
+        let physical_position =
+            u32::from_le_bytes(self.mmap[start + 4..start + ENTRY_SIZE].try_into().unwrap());
+        (relative_offset, physical_position)
+    }
+
+    /// Binary-searches for the entry with the greatest `relative_offset <=
+    /// target`, returning `(relative_offset, physical_position)`.
+    pub fn floor(&self, target: u32) -> Option<(u32, u32)> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+
+        let mut lo = 0;
+        let mut hi = len;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (relative_offset, _) = self.entry(mid);
+            if relative_offset <= target {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        if lo == 0 {
+            None
+        } else {
+            Some(self.entry(lo - 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_and_floor() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+        idx.append(0, 0).unwrap();
+        idx.append(5, 100).unwrap();
+        idx.append(10, 210).unwrap();
+
+        assert_eq!(idx.floor(0), Some((0, 0)));
+        assert_eq!(idx.floor(4), Some((0, 0)));
+        assert_eq!(idx.floor(5), Some((5, 100)));
+        assert_eq!(idx.floor(9), Some((5, 100)));
+        assert_eq!(idx.floor(10), Some((10, 210)));
+        assert_eq!(idx.floor(100), Some((10, 210)));
+    }
+
+    #[test]
+    fn test_floor_empty() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+        assert_eq!(idx.floor(0), None);
+    }
+
+    #[test]
+    fn test_recover_resumes_after_reopen() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        {
+            let mut idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+            idx.append(0, 0).unwrap();
+            idx.append(5, 100).unwrap();
+            idx.append(10, 210).unwrap();
+            idx.flush().unwrap();
+        }
+
+        let idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+        assert_eq!(idx.len(), 3);
+        assert_eq!(idx.floor(9), Some((5, 100)));
+        assert_eq!(idx.floor(10), Some((10, 210)));
+    }
+
+    #[test]
+    fn test_first_entry_is_not_mistaken_for_unwritten() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        {
+            let mut idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+            idx.append(0, 0).unwrap();
+            idx.flush().unwrap();
+        }
+
+        let idx = Index::new(tmp_dir.clone(), 0, 40, "").unwrap();
+        assert_eq!(idx.len(), 1);
+        assert_eq!(idx.floor(0), Some((0, 0)));
+    }
+}
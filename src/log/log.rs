@@ -4,28 +4,143 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
 use std::path::PathBuf;
 
+use super::index::Index;
+use super::occupancy::Occupancy;
+
+/// Size in bytes of a frame header: a little-endian `u32` payload length
+/// followed by a little-endian `u32` CRC32 of the payload.
+const HEADER_SIZE: usize = 8;
+
 #[derive(Debug, From)]
 pub enum Error {
     Io(io::Error),
     NoSpaceLeft,
     InvalidIndex,
+    Corrupt,
+}
+
+/// Reserves `len` bytes for `file` without eagerly zero-filling the
+/// underlying pages, via `posix_fallocate`. Falls back to `set_len` on
+/// filesystems that don't support it (and on non-Unix targets).
+#[cfg(unix)]
+fn allocate(file: &File, len: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let ret = unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        file.set_len(len)
+    }
+}
+
+#[cfg(not(unix))]
+fn allocate(file: &File, len: u64) -> io::Result<()> {
+    file.set_len(len)
+}
+
+/// Locates the end of the last written region of a sparse `file` via
+/// `SEEK_DATA`/`SEEK_HOLE`, so a freshly opened segment can find its true
+/// data boundary without scanning from zero. Returns `max_size` on targets
+/// without hole-punching support.
+#[cfg(unix)]
+fn data_end(file: &File, max_size: u64) -> io::Result<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut pos: libc::off_t = 0;
+    let mut end = 0i64;
+
+    loop {
+        let data_start = unsafe { libc::lseek(fd, pos, libc::SEEK_DATA) };
+        if data_start < 0 {
+            let err = io::Error::last_os_error();
+            return if err.raw_os_error() == Some(libc::ENXIO) {
+                Ok(end as u64)
+            } else {
+                Err(err)
+            };
+        }
+
+        let hole_start = unsafe { libc::lseek(fd, data_start, libc::SEEK_HOLE) };
+        end = if hole_start < 0 {
+            max_size as i64
+        } else {
+            hole_start
+        };
+
+        pos = end;
+        if end as u64 >= max_size {
+            break;
+        }
+    }
+
+    Ok(end as u64)
+}
+
+#[cfg(not(unix))]
+fn data_end(_file: &File, max_size: u64) -> io::Result<u64> {
+    Ok(max_size)
 }
 
 #[derive(Debug)]
 pub struct Log {
+    path: PathBuf,
+    suffix: String,
     file: File,
     base_offset: usize,
     max_size: usize,
+    max_cap: Option<usize>,
     offset: usize,
     mmap: MmapMut,
+    index: Index,
+    index_interval: usize,
+    bytes_since_index: usize,
+    next_offset: usize,
+    occupancy: Occupancy,
 }
 
 impl Log {
+    /// Opens a fixed-size segment: `write` fails with `Error::NoSpaceLeft`
+    /// once `max_size` is reached.
     pub fn new(
         path: PathBuf,
         base_offset: usize,
         max_size: usize,
         suffix: &str,
+        index_interval: usize,
+    ) -> Result<Log, io::Error> {
+        Log::open(path, base_offset, max_size, None, suffix, index_interval)
+    }
+
+    /// Opens a growable segment: once the mapping is full, `write` doubles
+    /// the backing file up to `max_cap` bytes and re-establishes the mapping
+    /// instead of failing.
+    pub fn with_growth(
+        path: PathBuf,
+        base_offset: usize,
+        initial_size: usize,
+        max_cap: usize,
+        suffix: &str,
+        index_interval: usize,
+    ) -> Result<Log, io::Error> {
+        Log::open(
+            path,
+            base_offset,
+            initial_size,
+            Some(max_cap),
+            suffix,
+            index_interval,
+        )
+    }
+
+    fn open(
+        path: PathBuf,
+        base_offset: usize,
+        max_size: usize,
+        max_cap: Option<usize>,
+        suffix: &str,
+        index_interval: usize,
     ) -> Result<Log, io::Error> {
         fs::create_dir_all(&path).unwrap();
         let segment_path = path.join(format!("{:020}.log{}", base_offset, suffix));
@@ -37,26 +152,59 @@ impl Log {
             .open(&segment_path)
             .unwrap();
 
-        file.set_len(max_size as u64)?;
+        let existing_len = file.metadata()?.len();
+        allocate(&file, (max_size as u64).max(existing_len))?;
+        // `allocate` is never asked for less than the file's current length,
+        // so a segment that was previously enlarged by `grow()` keeps its
+        // real on-disk size here (even on the `set_len` fallback path) --
+        // trust that over the caller-supplied `max_size`, or `recover()`
+        // would clamp its scan short and the next `write` would overwrite
+        // live data past the original size.
+        let max_size = file.metadata()?.len() as usize;
 
         let mmap = unsafe { MmapMut::map_mut(&file)? };
-        let offset = 0;
+        let index = Index::new(path.clone(), base_offset, max_size, suffix)?;
+        let capacity = (max_size / HEADER_SIZE).max(1);
+        let occupancy = Occupancy::new(path.clone(), base_offset, capacity, suffix)?;
 
-        Ok(Log {
+        let mut log = Log {
+            path,
+            suffix: suffix.to_string(),
             file,
             base_offset,
             max_size,
-            offset,
+            max_cap,
+            offset: 0,
             mmap,
-        })
+            index,
+            index_interval,
+            bytes_since_index: 0,
+            next_offset: 0,
+            occupancy,
+        };
+        log.recover();
+
+        Ok(log)
     }
 
     pub fn offset(&self) -> usize {
         self.offset
     }
 
+    pub fn base_offset(&self) -> usize {
+        self.base_offset
+    }
+
+    /// Number of messages appended to this segment so far -- the logical
+    /// offset that will be assigned to the next `write`.
+    pub fn message_count(&self) -> usize {
+        self.next_offset
+    }
+
     pub fn flush(&mut self) -> Result<(), Error> {
         self.mmap.flush_async()?;
+        self.index.flush()?;
+        self.occupancy.flush()?;
         Ok(())
     }
 
@@ -64,15 +212,69 @@ impl Log {
         (self.max_size - self.offset) >= size
     }
 
+    /// Doubles the backing file (capped at `max_cap`) and re-establishes the
+    /// mmap so the next `size` bytes fit. Only reachable from `&mut self`
+    /// write paths, since remapping invalidates any outstanding `&[u8]`
+    /// borrows returned by `read_at`/`read_record` -- the borrow checker
+    /// enforces this because those borrows carry the same `&mut self`
+    /// lifetime that `grow` requires.
+    fn grow(&mut self, size: usize) -> Result<(), Error> {
+        let cap = self.max_cap.ok_or(Error::NoSpaceLeft)?;
+
+        let mut new_len = self.max_size;
+        while new_len - self.offset < size {
+            if new_len >= cap {
+                return Err(Error::NoSpaceLeft);
+            }
+            new_len = (new_len * 2).min(cap);
+        }
+
+        self.mmap.flush_async()?;
+        allocate(&self.file, new_len as u64)?;
+        self.mmap = unsafe { MmapMut::map_mut(&self.file)? };
+        self.max_size = new_len;
+
+        let capacity = (new_len / HEADER_SIZE).max(1);
+        self.occupancy.ensure_capacity(capacity)?;
+        self.index.ensure_capacity(new_len)?;
+
+        Ok(())
+    }
+
+    /// Returns the byte position just past the last written region of the
+    /// segment, as reported by the filesystem's sparse-file bookkeeping.
+    pub fn data_end(&self) -> Result<usize, Error> {
+        Ok(data_end(&self.file, self.max_size as u64)? as usize)
+    }
+
+    /// Appends `buf` as a new frame: `[u32 length_le][u32 crc32_le][payload]`,
+    /// emitting a sparse index entry once `index_interval` bytes have been
+    /// written since the last one. Returns the logical offset assigned to
+    /// this message.
     pub fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-        let buf_size = buf.len();
-        if !self.fit(buf_size) {
-            return Err(Error::NoSpaceLeft);
+        let payload_size = buf.len();
+        let frame_size = HEADER_SIZE + payload_size;
+        if !self.fit(frame_size) {
+            self.grow(frame_size)?;
+        }
+
+        let crc = crc32fast::hash(buf);
+        let start = self.offset;
+        (&mut self.mmap[start..start + 4]).write_all(&(payload_size as u32).to_le_bytes())?;
+        (&mut self.mmap[start + 4..start + HEADER_SIZE]).write_all(&crc.to_le_bytes())?;
+        (&mut self.mmap[start + HEADER_SIZE..start + frame_size]).write_all(buf)?;
+
+        let logical_offset = self.next_offset;
+        self.offset += frame_size;
+        self.bytes_since_index += frame_size;
+        self.next_offset += 1;
+
+        if self.bytes_since_index >= self.index_interval {
+            self.index.append(logical_offset as u32, start as u32)?;
+            self.bytes_since_index = 0;
         }
 
-        self.offset += buf_size;
-        let size = (&mut self.mmap[(self.offset - buf_size)..(self.offset)]).write(buf)?;
-        Ok(size)
+        Ok(logical_offset)
     }
 
     pub fn read_at(&mut self, offset: usize, size: usize) -> Result<&[u8], Error> {
@@ -82,13 +284,206 @@ impl Log {
 
         Ok(&self.mmap[offset..(offset + size)])
     }
+
+    /// Reads the frame starting at physical position `pos`, validating its
+    /// CRC32, and returns the payload slice.
+    pub fn read_record(&mut self, pos: usize) -> Result<&[u8], Error> {
+        let (start, end, crc) = self.frame_bounds(pos)?;
+
+        let payload = &self.mmap[start..end];
+        if crc32fast::hash(payload) != crc {
+            return Err(Error::Corrupt);
+        }
+
+        Ok(payload)
+    }
+
+    /// Resolves a logical message offset to a physical byte position by
+    /// binary-searching the sparse index for the closest entry at or before
+    /// `logical`, then replaying frames forward from there until `logical`
+    /// is reached.
+    pub fn position_for_offset(&mut self, logical: usize) -> Result<usize, Error> {
+        if logical >= self.next_offset {
+            return Err(Error::InvalidIndex);
+        }
+
+        let (mut current, mut pos) = match self.index.floor(logical as u32) {
+            Some((relative_offset, physical_position)) => {
+                (relative_offset as usize, physical_position as usize)
+            }
+            None => (0, 0),
+        };
+
+        while current < logical {
+            let (_, end, _) = self.frame_bounds(pos)?;
+            pos = end;
+            current += 1;
+        }
+
+        Ok(pos)
+    }
+
+    /// Marks the record at physical position `pos` as dead, so `compact`
+    /// will drop it without rewriting the rest of the segment.
+    pub fn mark_dead(&mut self, pos: usize) -> Result<(), Error> {
+        let index = self.record_index_at(pos)?;
+        self.occupancy.mark_dead(index)?;
+        Ok(())
+    }
+
+    /// Fraction of records in the segment that are still live.
+    pub fn live_ratio(&self) -> f64 {
+        self.occupancy.live_ratio(self.next_offset)
+    }
+
+    /// Finds the logical index of the record whose frame starts at physical
+    /// position `pos`, by replaying frames forward from the start of the
+    /// segment.
+    fn record_index_at(&self, pos: usize) -> Result<usize, Error> {
+        let mut current_pos = 0;
+        let mut index = 0;
+
+        while current_pos < pos {
+            let (_, end, _) = self.frame_bounds(current_pos)?;
+            current_pos = end;
+            index += 1;
+        }
+
+        if current_pos != pos {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok(index)
+    }
+
+    fn segment_path(&self, suffix: &str) -> PathBuf {
+        self.path
+            .join(format!("{:020}.log{}", self.base_offset, suffix))
+    }
+
+    fn index_path(&self, suffix: &str) -> PathBuf {
+        self.path
+            .join(format!("{:020}.index{}", self.base_offset, suffix))
+    }
+
+    fn occupancy_path(&self, suffix: &str) -> PathBuf {
+        self.path
+            .join(format!("{:020}.occ{}", self.base_offset, suffix))
+    }
+
+    /// Walks live frames and copies them into a fresh segment file, rebuilding
+    /// the offset index as it goes, then atomically renames the compacted
+    /// segment (and its index) over the original.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        let suffix = self.suffix.clone();
+        let compact_suffix = format!("{}.compact", suffix);
+        let mut compacted = Log::open(
+            self.path.clone(),
+            self.base_offset,
+            self.max_size,
+            self.max_cap,
+            &compact_suffix,
+            self.index_interval,
+        )?;
+
+        let mut pos = 0;
+        let mut index = 0;
+        while pos < self.offset {
+            let (start, end, crc) = self.frame_bounds(pos)?;
+            if self.occupancy.is_live(index)? {
+                let payload = &self.mmap[start..end];
+                if crc32fast::hash(payload) != crc {
+                    return Err(Error::Corrupt);
+                }
+                compacted.write(payload)?;
+            }
+            pos = end;
+            index += 1;
+        }
+        compacted.flush()?;
+        drop(compacted);
+
+        fs::rename(self.segment_path(&compact_suffix), self.segment_path(&suffix))?;
+        fs::rename(self.index_path(&compact_suffix), self.index_path(&suffix))?;
+        fs::rename(
+            self.occupancy_path(&compact_suffix),
+            self.occupancy_path(&suffix),
+        )?;
+
+        *self = Log::open(
+            self.path.clone(),
+            self.base_offset,
+            self.max_size,
+            self.max_cap,
+            &suffix,
+            self.index_interval,
+        )?;
+
+        Ok(())
+    }
+
+    /// Reads the header at `pos` and returns the payload's `(start, end, crc)`
+    /// without validating the checksum.
+    fn frame_bounds(&self, pos: usize) -> Result<(usize, usize, u32), Error> {
+        if pos + HEADER_SIZE > self.mmap.len() {
+            return Err(Error::InvalidIndex);
+        }
+
+        let len = u32::from_le_bytes(self.mmap[pos..pos + 4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(self.mmap[pos + 4..pos + HEADER_SIZE].try_into().unwrap());
+        let start = pos + HEADER_SIZE;
+        let end = start + len;
+        if end > self.mmap.len() {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok((start, end, crc))
+    }
+
+    /// Scans frames from the start of the segment to find where the last
+    /// valid record ends, and sets `offset` there. Stops at the first
+    /// zero-length header (the pre-zeroed tail), a frame whose CRC fails, or
+    /// a frame whose length would run past `max_size` -- whichever comes
+    /// first. Bounded by `data_end()` so a sparse segment doesn't walk pages
+    /// the filesystem never wrote. Called on open so an existing segment
+    /// resumes appending at the correct position instead of clobbering data.
+    fn recover(&mut self) {
+        let bound = self.data_end().unwrap_or(self.max_size).min(self.max_size);
+        let mut pos = 0;
+        let mut count = 0;
+
+        loop {
+            if pos + HEADER_SIZE > bound {
+                break;
+            }
+
+            let (start, end, crc) = match self.frame_bounds(pos) {
+                Ok(bounds) => bounds,
+                Err(_) => break,
+            };
+            if end - start == 0 {
+                break;
+            }
+            if end > self.max_size {
+                break;
+            }
+            if crc32fast::hash(&self.mmap[start..end]) != crc {
+                break;
+            }
+
+            pos = end;
+            count += 1;
+        }
+
+        self.offset = pos;
+        self.next_offset = count;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     extern crate tempfile;
-    use std::fs;
     use tempfile::tempdir;
 
     #[test]
@@ -97,40 +492,253 @@ mod tests {
 
         let expected_file = tmp_dir.clone().join("00000000000000000000.log");
 
-        let l = Log::new(tmp_dir.clone(), 0, 10, "").unwrap();
+        let l = Log::new(tmp_dir.clone(), 0, 10, "", 16).unwrap();
 
         assert!(expected_file.as_path().exists());
         assert_eq!(l.offset(), 0);
     }
 
     #[test]
-    fn test_write() {
+    fn test_write_and_read_record() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 50, "", 16).unwrap();
+        let logical_offset = l.write(b"juca-bala").unwrap();
+        l.flush().unwrap();
+
+        assert_eq!(logical_offset, 0);
+        assert_eq!(l.offset(), HEADER_SIZE + 9);
+        assert_eq!(l.read_record(0).unwrap(), b"juca-bala");
+    }
+
+    #[test]
+    fn test_read_record_detects_corruption() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 50, "", 16).unwrap();
+        l.write(b"juca-bala").unwrap();
+        l.flush().unwrap();
+
+        l.mmap[HEADER_SIZE] = b'X';
+
+        assert!(matches!(l.read_record(0), Err(Error::Corrupt)));
+    }
+
+    #[test]
+    fn test_read_at() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+        fs::create_dir_all(tmp_dir.clone()).unwrap();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 20, "", 16).unwrap();
+        l.write(b"juca-bala").unwrap();
+        l.flush().unwrap();
+
+        assert_eq!(
+            l.read_at(0, 9).unwrap(),
+            &[9, 0, 0, 0, 168, 10, 148, 160, b'j']
+        );
+    }
+
+    #[test]
+    fn test_recover_resumes_after_reopen() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        {
+            let mut l = Log::new(tmp_dir.clone(), 0, 50, "", 16).unwrap();
+            l.write(b"juca-bala").unwrap();
+            l.flush().unwrap();
+        }
+
+        let l = Log::new(tmp_dir.clone(), 0, 50, "", 16).unwrap();
+        assert_eq!(l.offset(), HEADER_SIZE + 9);
+    }
+
+    #[test]
+    fn test_position_for_offset() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 10).unwrap();
+        let pos0 = l.write(b"one").unwrap();
+        let pos1 = l.write(b"two").unwrap();
+        let pos2 = l.write(b"three").unwrap();
+        l.flush().unwrap();
+
+        assert_eq!(pos0, 0);
+        assert_eq!(pos1, 1);
+        assert_eq!(pos2, 2);
+
+        assert_eq!(l.position_for_offset(0).unwrap(), 0);
+
+        let pos = l.position_for_offset(1).unwrap();
+        assert_eq!(l.read_record(pos).unwrap(), b"two");
+
+        let pos = l.position_for_offset(2).unwrap();
+        assert_eq!(l.read_record(pos).unwrap(), b"three");
+    }
+
+    #[test]
+    fn test_position_for_offset_rejects_offsets_past_end() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 10).unwrap();
+        l.write(b"one").unwrap();
+        l.flush().unwrap();
+
+        assert!(matches!(
+            l.position_for_offset(1),
+            Err(Error::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_index_entries_survive_reopen() {
         let tmp_dir = tempdir().unwrap().path().to_owned();
-        let expected_file = tmp_dir.clone().join("00000000000000000000.log");
 
-        let mut l = Log::new(tmp_dir.clone(), 0, 50, "").unwrap();
-        l.write(b"boom!-big-reveal!-i-turned-myself-into-a-pickle!")
+        {
+            let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 10).unwrap();
+            l.write(b"one").unwrap();
+            l.write(b"two").unwrap();
+            l.write(b"three").unwrap();
+            l.flush().unwrap();
+        }
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 10).unwrap();
+        assert_eq!(l.index.floor(1), Some((1, 11)));
+
+        let pos = l.position_for_offset(2).unwrap();
+        assert_eq!(l.read_record(pos).unwrap(), b"three");
+    }
+
+    #[test]
+    fn test_with_growth_remaps_on_demand() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::with_growth(tmp_dir.clone(), 0, 16, 1024, "", 16).unwrap();
+        l.write(b"this record does not fit in the initial mapping")
             .unwrap();
         l.flush().unwrap();
 
+        assert!(l.max_size > 16);
         assert_eq!(
-            fs::read_to_string(expected_file).unwrap(),
-            String::from("boom!-big-reveal!-i-turned-myself-into-a-pickle!\u{0}\u{0}")
+            l.read_record(0).unwrap(),
+            b"this record does not fit in the initial mapping"
         );
+    }
+
+    #[test]
+    fn test_with_growth_preserves_data_across_reopen() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+        let grown_max_size;
+
+        {
+            let mut l = Log::with_growth(tmp_dir.clone(), 0, 16, 1024, "", 16).unwrap();
+            l.write(b"this record does not fit in the initial mapping")
+                .unwrap();
+            l.flush().unwrap();
+            grown_max_size = l.max_size;
+            assert!(grown_max_size > 16);
+        }
 
-        assert_eq!(l.offset(), 48);
+        // Reopening with the original (now stale) initial_size must not
+        // clamp max_size back down and must not let a subsequent write
+        // overwrite the record persisted above.
+        let mut l = Log::with_growth(tmp_dir.clone(), 0, 16, 1024, "", 16).unwrap();
+        assert_eq!(l.max_size, grown_max_size);
+        assert_eq!(
+            l.read_record(0).unwrap(),
+            b"this record does not fit in the initial mapping"
+        );
+
+        let second_offset = l.write(b"appended after reopen").unwrap();
+        l.flush().unwrap();
+
+        assert_eq!(second_offset, 1);
+        assert_eq!(
+            l.read_record(0).unwrap(),
+            b"this record does not fit in the initial mapping"
+        );
+        let pos = l.position_for_offset(1).unwrap();
+        assert_eq!(l.read_record(pos).unwrap(), b"appended after reopen");
     }
 
     #[test]
-    fn test_read() {
+    fn test_fixed_size_log_fails_past_max_size() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 16, "", 16).unwrap();
+        assert!(matches!(
+            l.write(b"this record does not fit"),
+            Err(Error::NoSpaceLeft)
+        ));
+    }
+
+    #[test]
+    fn test_data_end_within_bounds() {
         let tmp_dir = tempdir().unwrap().path().to_owned();
-        fs::create_dir_all(tmp_dir.clone()).unwrap();
 
-        let mut l = Log::new(tmp_dir.clone(), 0, 20, "").unwrap();
+        let mut l = Log::new(tmp_dir.clone(), 0, 50, "", 16).unwrap();
         l.write(b"juca-bala").unwrap();
         l.flush().unwrap();
 
-        assert_eq!(l.read_at(0, 9).unwrap(), b"juca-bala");
-        assert_eq!(l.read_at(1, 7).unwrap(), b"uca-bal");
+        let end = l.data_end().unwrap();
+        assert!(end >= HEADER_SIZE + 9);
+        assert!(end <= 50);
+    }
+
+    #[test]
+    fn test_mark_dead_and_live_ratio() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 16).unwrap();
+        l.write(b"one").unwrap();
+        let pos1 = HEADER_SIZE + 3;
+        l.write(b"two").unwrap();
+        l.write(b"three").unwrap();
+        l.flush().unwrap();
+
+        assert_eq!(l.live_ratio(), 1.0);
+
+        l.mark_dead(pos1).unwrap();
+
+        assert!((l.live_ratio() - (2.0 / 3.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_compact_drops_dead_records() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::new(tmp_dir.clone(), 0, 200, "", 16).unwrap();
+        l.write(b"one").unwrap();
+        let pos1 = HEADER_SIZE + 3;
+        l.write(b"two").unwrap();
+        l.write(b"three").unwrap();
+        l.flush().unwrap();
+
+        l.mark_dead(pos1).unwrap();
+        l.compact().unwrap();
+
+        assert_eq!(l.message_count(), 2);
+        assert_eq!(l.live_ratio(), 1.0);
+        assert_eq!(l.read_record(0).unwrap(), b"one");
+
+        let pos = l.position_for_offset(1).unwrap();
+        assert_eq!(l.read_record(pos).unwrap(), b"three");
+    }
+
+    #[test]
+    fn test_mark_dead_after_growth_past_initial_capacity() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut l = Log::with_growth(tmp_dir.clone(), 0, 16, 1 << 20, "", 16).unwrap();
+        let mut last_pos = 0;
+        for _ in 0..100 {
+            last_pos = l.write(b"x").unwrap();
+        }
+        l.flush().unwrap();
+
+        let pos = l.position_for_offset(last_pos).unwrap();
+        l.mark_dead(pos).unwrap();
+
+        assert!((l.live_ratio() - (99.0 / 100.0)).abs() < f64::EPSILON);
     }
 }
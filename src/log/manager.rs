@@ -0,0 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::log::{Error, Log};
+
+/// Owns an ordered set of `Log` segments and routes writes to the active
+/// one, rolling to a fresh segment when it fills up. This is the
+/// multi-segment, append-only partitioned log that the `{base_offset:020}`
+/// file naming on `Log` implies.
+#[derive(Debug)]
+pub struct LogManager {
+    path: PathBuf,
+    segment_bytes: usize,
+    index_interval: usize,
+    segments: Vec<Log>,
+    base_offsets: Vec<usize>,
+}
+
+impl LogManager {
+    pub fn new(
+        path: PathBuf,
+        segment_bytes: usize,
+        index_interval: usize,
+    ) -> Result<LogManager, io::Error> {
+        let mut manager = LogManager {
+            path,
+            segment_bytes,
+            index_interval,
+            segments: Vec::new(),
+            base_offsets: Vec::new(),
+        };
+        manager.roll(0)?;
+
+        Ok(manager)
+    }
+
+    /// Discovers existing `*.log` files in `path`, sorts them by base
+    /// offset, and recovers each one (or starts a fresh segment at offset 0
+    /// if the directory has none).
+    pub fn open_dir(
+        path: PathBuf,
+        segment_bytes: usize,
+        index_interval: usize,
+    ) -> Result<LogManager, io::Error> {
+        let mut base_offsets = Vec::new();
+        if path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let file_name = entry?.file_name();
+                if let Some(stem) = file_name.to_string_lossy().strip_suffix(".log") {
+                    if let Ok(base_offset) = stem.parse::<usize>() {
+                        base_offsets.push(base_offset);
+                    }
+                }
+            }
+        }
+        base_offsets.sort_unstable();
+
+        let mut manager = LogManager {
+            path: path.clone(),
+            segment_bytes,
+            index_interval,
+            segments: Vec::new(),
+            base_offsets: Vec::new(),
+        };
+
+        if base_offsets.is_empty() {
+            manager.roll(0)?;
+        } else {
+            for base_offset in base_offsets {
+                let log = Log::new(path.clone(), base_offset, segment_bytes, "", index_interval)?;
+                manager.base_offsets.push(base_offset);
+                manager.segments.push(log);
+            }
+        }
+
+        Ok(manager)
+    }
+
+    fn roll(&mut self, base_offset: usize) -> Result<(), io::Error> {
+        let log = Log::new(
+            self.path.clone(),
+            base_offset,
+            self.segment_bytes,
+            "",
+            self.index_interval,
+        )?;
+        self.base_offsets.push(base_offset);
+        self.segments.push(log);
+
+        Ok(())
+    }
+
+    /// Writes `buf` to the active segment, rolling to a new segment (named
+    /// from the active segment's current base offset plus its message
+    /// count) when the active one is full. Returns the global logical
+    /// offset assigned to the message.
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let base_offset = *self.base_offsets.last().unwrap();
+
+        match self.segments.last_mut().unwrap().write(buf) {
+            Ok(local_offset) => Ok(base_offset + local_offset),
+            Err(Error::NoSpaceLeft) => {
+                let next_base_offset = base_offset + self.segments.last().unwrap().message_count();
+                self.roll(next_base_offset)?;
+
+                let local_offset = self.segments.last_mut().unwrap().write(buf)?;
+                Ok(next_base_offset + local_offset)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the `size`-byte record at `logical_offset`, selecting the
+    /// segment whose base-offset bracket contains it via binary search and
+    /// delegating to its offset index to find the physical position.
+    pub fn read(&mut self, logical_offset: usize, size: usize) -> Result<&[u8], Error> {
+        let idx = self.segment_index_for(logical_offset)?;
+        let base_offset = self.base_offsets[idx];
+        let relative_offset = logical_offset - base_offset;
+
+        let segment = &mut self.segments[idx];
+        let pos = segment.position_for_offset(relative_offset)?;
+        let payload = segment.read_record(pos)?;
+        if payload.len() != size {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok(payload)
+    }
+
+    fn segment_index_for(&self, logical_offset: usize) -> Result<usize, Error> {
+        match self.base_offsets.binary_search(&logical_offset) {
+            Ok(i) => Ok(i),
+            Err(0) => Err(Error::InvalidIndex),
+            Err(i) => Ok(i - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_rolls_to_new_segment_when_full() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut manager = LogManager::new(tmp_dir.clone(), 16, 8).unwrap();
+        let offset0 = manager.write(b"one").unwrap();
+        let offset1 = manager.write(b"two").unwrap();
+
+        assert_eq!(offset0, 0);
+        assert_eq!(offset1, 1);
+        assert_eq!(manager.segments.len(), 2);
+        assert_eq!(manager.base_offsets, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_read_across_segments() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut manager = LogManager::new(tmp_dir.clone(), 16, 8).unwrap();
+        manager.write(b"one").unwrap();
+        let offset1 = manager.write(b"two").unwrap();
+
+        assert_eq!(manager.read(offset1, 3).unwrap(), b"two");
+    }
+
+    #[test]
+    fn test_open_dir_recovers_existing_segments() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        {
+            let mut manager = LogManager::new(tmp_dir.clone(), 16, 8).unwrap();
+            manager.write(b"one").unwrap();
+            manager.write(b"two").unwrap();
+        }
+
+        let manager = LogManager::open_dir(tmp_dir.clone(), 16, 8).unwrap();
+        assert_eq!(manager.base_offsets, vec![0, 1]);
+    }
+}
@@ -0,0 +1,9 @@
+mod index;
+mod log;
+mod manager;
+mod occupancy;
+
+pub use index::Index;
+pub use log::{Error, Log};
+pub use manager::LogManager;
+pub use occupancy::Occupancy;
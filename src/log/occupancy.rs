@@ -0,0 +1,163 @@
+use memmap::MmapMut;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+use super::log::Error;
+
+const WORD_BITS: usize = 64;
+const WORD_BYTES: usize = 8;
+
+/// A per-record live/dead bitmap, sized in whole `u64` words as in the
+/// bucket-storage header convention: bit `i` tracks record `i`, set means
+/// dead. Persisted as a sibling `{base_offset:020}.occ` mmap file so marks
+/// survive a reopen without rewriting the segment.
+#[derive(Debug)]
+pub struct Occupancy {
+    #[allow(dead_code)]
+    file: File,
+    mmap: MmapMut,
+}
+
+impl Occupancy {
+    pub fn new(
+        path: PathBuf,
+        base_offset: usize,
+        capacity: usize,
+        suffix: &str,
+    ) -> Result<Occupancy, io::Error> {
+        fs::create_dir_all(&path).unwrap();
+        let occ_path = path.join(format!("{:020}.occ{}", base_offset, suffix));
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .open(&occ_path)
+            .unwrap();
+
+        let words = (capacity + WORD_BITS - 1) / WORD_BITS;
+        let max_size = (words.max(1) * WORD_BYTES) as u64;
+        file.set_len(max_size)?;
+
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        Ok(Occupancy { file, mmap })
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.mmap.flush_async()?;
+        Ok(())
+    }
+
+    /// Grows the bitmap, if needed, to track at least `capacity` records.
+    /// Mirrors `Log::grow` so the occupancy file keeps pace when a growable
+    /// segment outgrows the capacity it was opened with.
+    pub fn ensure_capacity(&mut self, capacity: usize) -> Result<(), Error> {
+        let words = (capacity + WORD_BITS - 1) / WORD_BITS;
+        let needed_size = words.max(1) * WORD_BYTES;
+        if needed_size <= self.mmap.len() {
+            return Ok(());
+        }
+
+        self.mmap.flush_async()?;
+        self.file.set_len(needed_size as u64)?;
+        self.mmap = unsafe { MmapMut::map_mut(&self.file)? };
+
+        Ok(())
+    }
+
+    /// Marks record `index` as dead without rewriting the segment.
+    pub fn mark_dead(&mut self, index: usize) -> Result<(), Error> {
+        let start = self.word_start(index)?;
+        let mut value =
+            u64::from_le_bytes(self.mmap[start..start + WORD_BYTES].try_into().unwrap());
+        value |= 1 << (index % WORD_BITS);
+        self.mmap[start..start + WORD_BYTES].copy_from_slice(&value.to_le_bytes());
+
+        Ok(())
+    }
+
+    pub fn is_live(&self, index: usize) -> Result<bool, Error> {
+        let start = self.word_start(index)?;
+        let value =
+            u64::from_le_bytes(self.mmap[start..start + WORD_BYTES].try_into().unwrap());
+
+        Ok((value >> (index % WORD_BITS)) & 1 == 0)
+    }
+
+    fn word_start(&self, index: usize) -> Result<usize, Error> {
+        let start = (index / WORD_BITS) * WORD_BYTES;
+        if start + WORD_BYTES > self.mmap.len() {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok(start)
+    }
+
+    /// Fraction of the first `total` records still marked live.
+    pub fn live_ratio(&self, total: usize) -> f64 {
+        if total == 0 {
+            return 1.0;
+        }
+
+        let live = (0..total).filter(|&i| self.is_live(i).unwrap_or(false)).count();
+        live as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_mark_dead_and_is_live() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut occ = Occupancy::new(tmp_dir.clone(), 0, 3, "").unwrap();
+        assert!(occ.is_live(0).unwrap());
+        assert!(occ.is_live(1).unwrap());
+        assert!(occ.is_live(2).unwrap());
+
+        occ.mark_dead(1).unwrap();
+
+        assert!(occ.is_live(0).unwrap());
+        assert!(!occ.is_live(1).unwrap());
+        assert!(occ.is_live(2).unwrap());
+    }
+
+    #[test]
+    fn test_live_ratio() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut occ = Occupancy::new(tmp_dir.clone(), 0, 4, "").unwrap();
+        occ.mark_dead(0).unwrap();
+        occ.mark_dead(2).unwrap();
+
+        assert_eq!(occ.live_ratio(4), 0.5);
+    }
+
+    #[test]
+    fn test_out_of_bounds_access_is_an_error_not_a_panic() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut occ = Occupancy::new(tmp_dir.clone(), 0, 3, "").unwrap();
+        assert!(matches!(occ.is_live(1000), Err(Error::InvalidIndex)));
+        assert!(matches!(occ.mark_dead(1000), Err(Error::InvalidIndex)));
+    }
+
+    #[test]
+    fn test_ensure_capacity_grows_bitmap() {
+        let tmp_dir = tempdir().unwrap().path().to_owned();
+
+        let mut occ = Occupancy::new(tmp_dir.clone(), 0, 3, "").unwrap();
+        assert!(occ.is_live(1000).is_err());
+
+        occ.ensure_capacity(1000).unwrap();
+        assert!(occ.is_live(1000).unwrap());
+
+        occ.mark_dead(1000).unwrap();
+        assert!(!occ.is_live(1000).unwrap());
+    }
+}